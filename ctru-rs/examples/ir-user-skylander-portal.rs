@@ -4,10 +4,11 @@
 
 use ctru::prelude::*;
 use ctru::services::gfx::{Flush, Swap};
-use ctru::services::ir_user::{CirclePadProInputResponse, ConnectionStatus, IrUser};
-use ctru::services::svc::HandleExt;
+use ctru::services::ir_user::codec::{CirclePadProCodec, IrChannel};
+use ctru::services::ir_user::connection::{IrConnectionDriver, IrEvent};
+use ctru::services::ir_user::{CIRCLE_PAD_PRO_DEVICE_ID, IrUser};
 use ctru_sys::Handle;
-use std::time::Duration;
+use std::time::Instant;
 
 // Configuration for this demo of the Skylander (not general purpose ir:USER values).
 const PACKET_COUNT: usize = 8;
@@ -44,22 +45,13 @@ fn main() {
             break;
         }
 
-        // Check if we've received a packet from the Skylander
-        let packet_received = demo
-            .receive_packet_event
-            .wait_for_event(Duration::ZERO)
-            .is_ok();
-        if packet_received {
-            demo.handle_packets();
-        }
+        // Check if we've received a packet
+        demo.handle_packets();
 
-        // Check if we've sent a packet
-        let packet_sent = demo
-            .send_packet_event
-            .wait_for_event(Duration::ZERO)
-            .is_ok();
-        if packet_sent {
-            demo.handle_packets();
+        // Notice if an established connection dropped out from under us
+        if is_connected && demo.poll_connection_dropped() {
+            println!("Lost connection to the portal");
+            is_connected = false;
         }
 
         // Check if we should start the connection
@@ -79,10 +71,10 @@ fn main() {
 struct SkylanderPortalDemo<'screen> {
     top_console: Console<'screen>,
     bottom_console: Console<'screen>,
-    ir_user: IrUser,
-    connection_status_event: Handle,
-    receive_packet_event: Handle,
-    send_packet_event: Handle,
+    channel: IrChannel<CirclePadProCodec>,
+    connection: IrConnectionDriver,
+    recv_event: Handle,
+    send_event: Handle,
 }
 
 enum ConnectionResult {
@@ -106,78 +98,91 @@ impl<'screen> SkylanderPortalDemo<'screen> {
             PACKET_COUNT,
             PACKET_BUFFER_SIZE,
             PACKET_COUNT,
-            3
+            3,
         )
         .expect("Couldn't initialize ir:USER service");
         println!("ir:USER service initialized");
 
-        // Get event handles
-        let receive_packet_event = ir_user
+        let recv_event = ir_user
             .get_recv_event()
             .expect("Couldn't get ir:USER recv event");
-        let send_packet_event = ir_user
+        let send_event = ir_user
             .get_send_event()
             .expect("Couldn't get ir:USER send event");
-        let connection_status_event = ir_user
-            .get_connection_status_event()
-            .expect("Couldn't get ir:USER connection status event");
+
+        let channel = IrChannel::new(ir_user, CirclePadProCodec)
+            .expect("Couldn't set up ir:USER packet channel");
 
         Self {
             top_console,
             bottom_console,
-            ir_user,
-            connection_status_event,
-            receive_packet_event,
-            send_packet_event,
+            channel,
+            connection: IrConnectionDriver::new(),
+            recv_event,
+            send_event,
         }
     }
 
+    /// Poll the connection driver outside of an active connect attempt,
+    /// reporting whether it noticed the connection drop.
+    fn poll_connection_dropped(&mut self) -> bool {
+        matches!(
+            self.connection.poll(
+                Instant::now(),
+                self.channel.ir_user(),
+                self.recv_event,
+                self.send_event,
+            ),
+            Some(IrEvent::Disconnected)
+        )
+    }
+
     fn print_status_info(&mut self) {
         self.top_console.select();
         self.top_console.clear();
-        println!("{:#x?}", self.ir_user.get_status_info());
+        println!("{:#x?}", self.channel.ir_user().get_status_info());
         self.top_console.flush_buffers();
         self.top_console.swap_buffers();
         self.bottom_console.select();
     }
 
     fn connect_to_portal(&mut self, hid: &mut Hid) -> ConnectionResult {
-        // Connection loop
+        // The portal identifies itself the same way a Circle Pad Pro does.
+        self.connection
+            .connect(
+                Instant::now(),
+                self.channel.ir_user(),
+                CIRCLE_PAD_PRO_DEVICE_ID,
+            )
+            .expect("Couldn't request a connection to the portal");
+
+        // Drive the connection state machine until it reports we're
+        // connected, instead of hand-rolling wait_for_event/retry loops.
         loop {
             hid.scan_input();
             if hid.keys_held().contains(KeyPad::START) {
                 return ConnectionResult::Canceled;
             }
 
-            // Wait for the connection to establish
-            if let Err(e) = self
-                .connection_status_event
-                .wait_for_event(Duration::from_millis(100))
-            {
-                if !e.is_timeout() {
-                    panic!("Couldn't initialize Skylander connection: {e}");
-                }
-            }
-
             self.print_status_info();
-            if self.ir_user.get_status_info().connection_status == ConnectionStatus::Connected {
-                println!("Connected!");
-                break;
-            }
-
-            // If not connected (ex. timeout), disconnect so we can retry
-            self.ir_user
-                .disconnect()
-                .expect("Failed to disconnect Skylander connection");
 
-            // Wait for the disconnect to go through
-            if let Err(e) = self
-                .connection_status_event
-                .wait_for_event(Duration::from_millis(100))
-            {
-                if !e.is_timeout() {
-                    panic!("Couldn't initialize Skylander connection: {e}");
+            let event = self.connection.poll(
+                Instant::now(),
+                self.channel.ir_user(),
+                self.recv_event,
+                self.send_event,
+            );
+            match event {
+                Some(IrEvent::Connected {
+                    duration_since_intent,
+                }) => {
+                    println!("Connected! (took {duration_since_intent:?})");
+                    break;
                 }
+                Some(IrEvent::Disconnected) => {
+                    println!("Connect attempt failed, retrying after backoff");
+                }
+                Some(IrEvent::PacketReady) | Some(IrEvent::PacketSent) | None => {}
             }
         }
 
@@ -188,15 +193,15 @@ impl<'screen> SkylanderPortalDemo<'screen> {
                 return ConnectionResult::Canceled;
             }
 
-            // Wait for the response
-            let recv_event_result = self
-                .receive_packet_event
-                .wait_for_event(Duration::from_millis(100));
             self.print_status_info();
 
-            if recv_event_result.is_ok() {
+            if self
+                .channel
+                .recv()
+                .expect("Packets should be well formed")
+                .is_some()
+            {
                 println!("Got first packet from portal");
-                self.handle_packets();
                 break;
             }
 
@@ -207,56 +212,22 @@ impl<'screen> SkylanderPortalDemo<'screen> {
     }
 
     fn handle_packets(&mut self) {
-        let packets = self
-            .ir_user
-            .get_packets()
-            .expect("Packets should be well formed");
-        let packet_count = packets.len();
-        let Some(last_packet) = packets.last() else {
+        let Some(cpp_response) = self.channel.recv().expect("Packets should be well formed")
+        else {
             return;
         };
-        let status_info = self.ir_user.get_status_info();
-        let cpp_response = CirclePadProInputResponse::try_from(last_packet)
-            .expect("Failed to parse CPP response from IR packet");
+
+        let status_info = self.channel.ir_user().get_status_info();
 
         // Write data to top screen
         self.top_console.select();
         self.top_console.clear();
         println!("{:x?}", status_info);
-
-        self.ir_user.process_shared_memory(|ir_mem| {
-            println!("\nReceiveBufferInfo:");
-            print_buffer_as_hex(&ir_mem[0x10..0x20]);
-
-            println!("\nReceiveBuffer:");
-            print_buffer_as_hex(&ir_mem[0x20..0x20 + PACKET_BUFFER_SIZE]);
-            println!();
-        });
-
-        println!("\nPacket count: {packet_count}");
-        println!("{last_packet:02x?}");
         println!("\n{cpp_response:#02x?}");
 
         // Flush output and switch back to bottom screen
         self.top_console.flush_buffers();
         self.top_console.swap_buffers();
         self.bottom_console.select();
-
-        // Done handling the packets, release them
-        self.ir_user
-            .release_received_data(packet_count as u32)
-            .expect("Failed to release ir:USER packet");
-
-    }
-}
-
-fn print_buffer_as_hex(buffer: &[u8]) {
-    let mut counter = 0;
-    for byte in buffer {
-        print!("{byte:02x} ");
-        counter += 1;
-        if counter % 16 == 0 {
-            println!();
-        }
     }
 }
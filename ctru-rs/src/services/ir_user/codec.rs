@@ -0,0 +1,144 @@
+//! Typed, framed packet layer over [`IrUser`](super::IrUser) shared memory.
+//!
+//! This mirrors the codec/channel split audioipc2 uses for its own framed
+//! IPC: an [`IrPacketCodec`] knows how to turn a raw shared-memory packet
+//! into a typed item (and a typed item back into bytes to send), and
+//! [`IrChannel`] drives that codec against an [`IrUser`] handle, releasing
+//! consumed receive-buffer packets automatically so callers never touch the
+//! shared memory directly.
+
+use super::IrUser;
+use crate::services::svc::HandleExt;
+use crate::Result;
+use bytes::BytesMut;
+use ctru_sys::Handle;
+use std::time::Duration;
+
+/// Encodes/decodes a single ir:USER packet to/from a typed item.
+///
+/// Implement this for accessory-specific packet formats; [`CirclePadProCodec`]
+/// is the built-in implementation for the Circle Pad Pro / Skylander-portal
+/// response format.
+pub trait IrPacketCodec {
+    /// The typed item this codec produces from, and consumes to build,
+    /// packets.
+    type Item;
+
+    /// Serialize `item`, appending the resulting bytes to `dst`.
+    fn encode(&self, item: &Self::Item, dst: &mut BytesMut);
+
+    /// Try to decode a complete item out of `src`.
+    ///
+    /// Returns `Ok(None)` if `src` isn't a packet this codec understands.
+    fn decode(&self, src: &[u8]) -> Result<Option<Self::Item>>;
+}
+
+/// A framed channel over an [`IrUser`] handle.
+///
+/// Wraps an already-initialized [`IrUser`] and a codec `C`, pulling complete
+/// frames out of the receive buffer and releasing them once consumed, and
+/// serializing outgoing items into the send buffer.
+pub struct IrChannel<C: IrPacketCodec> {
+    ir_user: IrUser,
+    codec: C,
+    receive_packet_event: Handle,
+}
+
+impl<C: IrPacketCodec> IrChannel<C> {
+    /// Wrap an already-initialized [`IrUser`] handle in a framed channel
+    /// driven by `codec`.
+    pub fn new(ir_user: IrUser, codec: C) -> Result<Self> {
+        let receive_packet_event = ir_user.get_recv_event()?;
+
+        Ok(Self {
+            ir_user,
+            codec,
+            receive_packet_event,
+        })
+    }
+
+    /// Poll for the next complete item, decoding and releasing the
+    /// shared-memory packet(s) it came from.
+    ///
+    /// Returns `Ok(None)` if nothing new has arrived yet.
+    pub fn recv(&mut self) -> Result<Option<C::Item>> {
+        if self
+            .receive_packet_event
+            .wait_for_event(Duration::ZERO)
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        let packets = self.ir_user.get_packets()?;
+        let packet_count = packets.len();
+
+        // Only the last packet in a batch matters for report-style
+        // accessories (like the CPP), but run every packet through the
+        // codec so multi-packet frame formats can still assemble themselves.
+        let mut item = None;
+        for packet in &packets {
+            if let Some(decoded) = self.codec.decode(packet)? {
+                item = Some(decoded);
+            }
+        }
+
+        if packet_count > 0 {
+            self.ir_user.release_received_data(packet_count as u32)?;
+        }
+
+        Ok(item)
+    }
+
+    /// Encode and send `item` through the underlying [`IrUser`] handle.
+    pub fn send(&mut self, item: &C::Item) -> Result<()> {
+        let mut dst = BytesMut::new();
+        self.codec.encode(item, &mut dst);
+        self.ir_user.send_packet(&dst)
+    }
+
+    /// Access the underlying [`IrUser`] handle, e.g. to check connection
+    /// status or wait on its other events.
+    pub fn ir_user(&self) -> &IrUser {
+        &self.ir_user
+    }
+}
+
+/// Built-in [`IrPacketCodec`] for the Circle Pad Pro / Skylander-portal
+/// response format.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CirclePadProCodec;
+
+impl IrPacketCodec for CirclePadProCodec {
+    type Item = super::CirclePadProInputResponse;
+
+    fn encode(&self, _item: &Self::Item, _dst: &mut BytesMut) {
+        // This accessory only ever pushes input reports; there's nothing
+        // for the host to send in this direction through this codec.
+    }
+
+    fn decode(&self, src: &[u8]) -> Result<Option<Self::Item>> {
+        Ok(Some(Self::Item::try_from(src)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle_pad_pro_codec_decodes_a_report() {
+        let data = [0x00, 0x00, 0x00, 0x00, 0xFF, 0x1F];
+
+        let decoded = CirclePadProCodec.decode(&data).unwrap();
+
+        assert!(decoded.is_some());
+        assert_eq!(decoded.unwrap().battery_level, 0x1F);
+    }
+
+    #[test]
+    fn circle_pad_pro_codec_errors_on_short_packets() {
+        let data = [0x00; 4];
+        assert!(CirclePadProCodec.decode(&data).is_err());
+    }
+}
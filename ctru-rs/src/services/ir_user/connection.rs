@@ -0,0 +1,367 @@
+//! Event-driven connection state machine for [`IrUser`](super::IrUser).
+//!
+//! Modeled on quinn-proto's `Connection` poll loop: rather than the caller
+//! hand-rolling `wait_for_event(Duration)` loops with manual
+//! disconnect-and-retry and a `panic!` on anything but a timeout, it drives
+//! an [`IrConnectionDriver`] with the current time, the service's recv and
+//! send event handles and the [`IrUser`] itself (whose status block supplies
+//! the connection status), and reacts to the [`IrEvent`]s it returns. This is
+//! the only place that issues the `IRU_RequireConnection` request, so there's
+//! a single connect/retry/backoff path shared by every accessory built on
+//! [`IrUser`] - no device type should hand-roll its own.
+//! [`IrConnectionDriver::poll_timeout`] tells the caller when it needs to
+//! wake up again even if no kernel event has fired.
+
+use super::{ConnectionStatus, IrUser};
+use crate::services::svc::HandleExt;
+use crate::Result;
+use ctru_sys::Handle;
+use std::time::{Duration, Instant};
+
+/// The state of an ir:USER connection attempt.
+#[derive(Copy, Clone, Debug)]
+pub enum IrConnection {
+    /// No connection attempt is in progress.
+    Disconnected,
+    /// A connection attempt is in progress, started at `started_at`.
+    Connecting { started_at: Instant },
+    /// The connection is established.
+    Connected,
+    /// A previous attempt failed or timed out; waiting until `until` before
+    /// retrying.
+    Backoff { until: Instant },
+}
+
+/// An event surfaced by [`IrConnectionDriver::poll`].
+#[derive(Copy, Clone, Debug)]
+pub enum IrEvent {
+    /// The connection was established. `duration_since_intent` is how long
+    /// it took since [`IrConnectionDriver::connect`] was called (or, when
+    /// reconnecting after an established connection dropped, since that
+    /// automatic retry began), for diagnosing slow portals.
+    Connected { duration_since_intent: Duration },
+    /// The connection was lost, or a connect attempt timed out.
+    Disconnected,
+    /// A complete packet is ready to be read out of the receive buffer.
+    PacketReady,
+    /// A previously queued packet finished sending.
+    PacketSent,
+}
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(4);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Drives an [`IrUser`] connection attempt through connect, timeout and
+/// exponential backoff, so the caller only has to call [`poll`](Self::poll)
+/// on a timer (see [`poll_timeout`](Self::poll_timeout)), passing in the recv
+/// and send event handles, instead of waiting on each event and tracking the
+/// connection status itself.
+pub struct IrConnectionDriver {
+    state: IrConnection,
+    connect_intent_at: Option<Instant>,
+    backoff: Duration,
+    device_id: u8,
+}
+
+impl IrConnectionDriver {
+    /// Create a driver that starts out disconnected. Call
+    /// [`connect`](Self::connect) to kick off the first attempt.
+    pub fn new() -> Self {
+        Self {
+            state: IrConnection::Disconnected,
+            connect_intent_at: None,
+            backoff: INITIAL_BACKOFF,
+            device_id: 0,
+        }
+    }
+
+    /// The current connection state.
+    pub fn state(&self) -> IrConnection {
+        self.state
+    }
+
+    /// Issue an `IRU_RequireConnection` request for `device_id` and record
+    /// the intent to connect, starting (or restarting) the attempt.
+    ///
+    /// Any later automatic retry (after a timeout or dropped connection)
+    /// reissues the request for this same `device_id`; call this again to
+    /// switch to a different one.
+    pub fn connect(&mut self, now: Instant, ir_user: &IrUser, device_id: u8) -> Result<()> {
+        ir_user.require_connection(device_id)?;
+        self.device_id = device_id;
+        self.begin_connecting(now);
+        Ok(())
+    }
+
+    fn begin_connecting(&mut self, now: Instant) {
+        self.state = IrConnection::Connecting { started_at: now };
+        self.connect_intent_at.get_or_insert(now);
+    }
+
+    /// Advance the state machine and report the next event, if any.
+    ///
+    /// `recv_event` and `send_event` are checked (non-blocking) on every
+    /// call, and a completed packet is reported only when no connection
+    /// state transition happens in the same call. The connection status is
+    /// read from `ir_user`'s status block (the connection-status kernel event
+    /// is not waited on), and that reading drives the
+    /// connect/timeout/backoff transitions below. When backoff expires this
+    /// also reissues the `IRU_RequireConnection` request for the device
+    /// passed to [`connect`](Self::connect).
+    pub fn poll(
+        &mut self,
+        now: Instant,
+        ir_user: &IrUser,
+        recv_event: Handle,
+        send_event: Handle,
+    ) -> Option<IrEvent> {
+        let recv_ready = recv_event.wait_for_event(Duration::ZERO).is_ok();
+        let send_ready = send_event.wait_for_event(Duration::ZERO).is_ok();
+        let status = ir_user.get_status_info().connection_status;
+
+        let (event, should_disconnect, should_reconnect) =
+            self.advance(now, status, recv_ready, send_ready);
+
+        if should_disconnect {
+            let _ = ir_user.disconnect();
+        }
+
+        if should_reconnect {
+            let _ = ir_user.require_connection(self.device_id);
+        }
+
+        event
+    }
+
+    /// The pure transition logic behind [`poll`](Self::poll): given the
+    /// current time, the latest connection status reading, and whether the
+    /// recv/send events fired, returns the event to report (if any), whether
+    /// the caller should tell ir:USER to disconnect, and whether it should
+    /// reissue the connect request (backoff just expired).
+    ///
+    /// Kept separate from [`poll`](Self::poll) so the state machine can be
+    /// exercised without a real `IrUser`/kernel events.
+    fn advance(
+        &mut self,
+        now: Instant,
+        status: ConnectionStatus,
+        recv_ready: bool,
+        send_ready: bool,
+    ) -> (Option<IrEvent>, bool, bool) {
+        let connection_event = match self.state {
+            IrConnection::Disconnected => None,
+            IrConnection::Connecting { started_at } => {
+                if status == ConnectionStatus::Connected {
+                    self.state = IrConnection::Connected;
+                    self.backoff = INITIAL_BACKOFF;
+
+                    let duration_since_intent = self
+                        .connect_intent_at
+                        .take()
+                        .map(|at| now.saturating_duration_since(at))
+                        .unwrap_or_default();
+
+                    Some((
+                        IrEvent::Connected {
+                            duration_since_intent,
+                        },
+                        false,
+                    ))
+                } else if now.saturating_duration_since(started_at) >= CONNECT_TIMEOUT {
+                    self.enter_backoff(now);
+                    Some((IrEvent::Disconnected, true))
+                } else {
+                    None
+                }
+            }
+            IrConnection::Connected => {
+                if status != ConnectionStatus::Connected {
+                    self.enter_backoff(now);
+                    Some((IrEvent::Disconnected, true))
+                } else {
+                    None
+                }
+            }
+            IrConnection::Backoff { until } => {
+                if now >= until {
+                    self.begin_connecting(now);
+                    return (None, false, true);
+                }
+
+                None
+            }
+        };
+
+        // A connection-status transition always takes priority over a
+        // packet event in the same tick; the caller needs to know about it
+        // before it tries to act on stale packet data.
+        match connection_event {
+            Some((event, should_disconnect)) => (Some(event), should_disconnect, false),
+            None => (Self::packet_event(recv_ready, send_ready), false, false),
+        }
+    }
+
+    /// Turn non-blocking recv/send event readiness into an [`IrEvent`],
+    /// recv taking priority if both fired.
+    fn packet_event(recv_ready: bool, send_ready: bool) -> Option<IrEvent> {
+        if recv_ready {
+            Some(IrEvent::PacketReady)
+        } else if send_ready {
+            Some(IrEvent::PacketSent)
+        } else {
+            None
+        }
+    }
+
+    /// The latest time at which the caller should call [`poll`](Self::poll)
+    /// again, even if no kernel event has fired in the meantime (e.g. to
+    /// notice a connect timeout or a backoff expiring). `None` means there's
+    /// nothing to wait for right now.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        match self.state {
+            IrConnection::Disconnected | IrConnection::Connected => None,
+            IrConnection::Connecting { started_at } => Some(started_at + CONNECT_TIMEOUT),
+            IrConnection::Backoff { until } => Some(until),
+        }
+    }
+
+    fn enter_backoff(&mut self, now: Instant) {
+        self.state = IrConnection::Backoff {
+            until: now + self.backoff,
+        };
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+impl Default for IrConnectionDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_times_out_backs_off_retries_then_connects() {
+        let t0 = Instant::now();
+        let mut driver = IrConnectionDriver::new();
+
+        driver.begin_connecting(t0);
+        assert!(matches!(driver.state(), IrConnection::Connecting { .. }));
+
+        // Not connected yet, and not timed out: no event.
+        let (event, disconnect, reconnect) =
+            driver.advance(t0, ConnectionStatus::Disconnected, false, false);
+        assert!(event.is_none());
+        assert!(!disconnect);
+        assert!(!reconnect);
+
+        // Timed out: should report Disconnected and ask the caller to
+        // disconnect, then move into backoff.
+        let (event, disconnect, reconnect) = driver.advance(
+            t0 + CONNECT_TIMEOUT,
+            ConnectionStatus::Disconnected,
+            false,
+            false,
+        );
+        assert!(matches!(event, Some(IrEvent::Disconnected)));
+        assert!(disconnect);
+        assert!(!reconnect);
+        let IrConnection::Backoff { until } = driver.state() else {
+            panic!("expected Backoff state, got {:?}", driver.state());
+        };
+
+        // Still backing off: no event, no transition yet.
+        let (event, disconnect, reconnect) = driver.advance(
+            until - Duration::from_millis(1),
+            ConnectionStatus::Disconnected,
+            false,
+            false,
+        );
+        assert!(event.is_none());
+        assert!(!disconnect);
+        assert!(!reconnect);
+        assert!(matches!(driver.state(), IrConnection::Backoff { .. }));
+
+        // Backoff expired: retries by moving back into Connecting, and asks
+        // the caller to reissue the connect request.
+        let (event, disconnect, reconnect) =
+            driver.advance(until, ConnectionStatus::Disconnected, false, false);
+        assert!(event.is_none());
+        assert!(!disconnect);
+        assert!(reconnect);
+        assert!(matches!(driver.state(), IrConnection::Connecting { .. }));
+
+        // Now the device reports connected.
+        let (event, disconnect, reconnect) =
+            driver.advance(until, ConnectionStatus::Connected, false, false);
+        assert!(matches!(event, Some(IrEvent::Connected { .. })));
+        assert!(!disconnect);
+        assert!(!reconnect);
+        assert!(matches!(driver.state(), IrConnection::Connected));
+    }
+
+    #[test]
+    fn dropped_connection_is_reported_and_disconnected() {
+        let t0 = Instant::now();
+        let mut driver = IrConnectionDriver::new();
+        driver.begin_connecting(t0);
+        driver.advance(t0, ConnectionStatus::Connected, false, false);
+        assert!(matches!(driver.state(), IrConnection::Connected));
+
+        let (event, disconnect, reconnect) =
+            driver.advance(t0, ConnectionStatus::Disconnected, false, false);
+        assert!(matches!(event, Some(IrEvent::Disconnected)));
+        assert!(disconnect);
+        assert!(!reconnect);
+        assert!(matches!(driver.state(), IrConnection::Backoff { .. }));
+    }
+
+    #[test]
+    fn packet_events_only_surface_once_stable() {
+        let t0 = Instant::now();
+        let mut driver = IrConnectionDriver::new();
+        driver.begin_connecting(t0);
+        driver.advance(t0, ConnectionStatus::Connected, false, false);
+
+        let (event, disconnect, _) = driver.advance(t0, ConnectionStatus::Connected, true, false);
+        assert!(matches!(event, Some(IrEvent::PacketReady)));
+        assert!(!disconnect);
+
+        let (event, _, _) = driver.advance(t0, ConnectionStatus::Connected, false, true);
+        assert!(matches!(event, Some(IrEvent::PacketSent)));
+
+        let (event, _, _) = driver.advance(t0, ConnectionStatus::Connected, false, false);
+        assert!(event.is_none());
+
+        // A packet event in the same tick as a connection drop is not
+        // surfaced; the drop is.
+        let (event, _, _) = driver.advance(t0, ConnectionStatus::Disconnected, true, true);
+        assert!(matches!(event, Some(IrEvent::Disconnected)));
+    }
+
+    #[test]
+    fn poll_timeout_tracks_connecting_and_backoff_deadlines() {
+        let t0 = Instant::now();
+        let mut driver = IrConnectionDriver::new();
+
+        assert!(driver.poll_timeout().is_none());
+
+        driver.begin_connecting(t0);
+        assert_eq!(driver.poll_timeout(), Some(t0 + CONNECT_TIMEOUT));
+
+        driver.advance(
+            t0 + CONNECT_TIMEOUT,
+            ConnectionStatus::Disconnected,
+            false,
+            false,
+        );
+        let IrConnection::Backoff { until } = driver.state() else {
+            panic!("expected Backoff state, got {:?}", driver.state());
+        };
+        assert_eq!(driver.poll_timeout(), Some(until));
+    }
+}
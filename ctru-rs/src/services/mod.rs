@@ -0,0 +1,13 @@
+//! Device services.
+//!
+//! This module contains safe wrappers around the system services exposed by
+//! `ctru-sys`. Services generally follow an RAII pattern: constructing the
+//! type (usually via a `new` function) initializes the service, and dropping
+//! it releases the associated resources.
+
+pub mod apt;
+pub mod gfx;
+pub mod hid;
+pub mod ir_user;
+pub mod ndm;
+pub mod svc;
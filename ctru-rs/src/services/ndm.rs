@@ -0,0 +1,126 @@
+//! Network Daemon Manager (ndm:u) service.
+//!
+//! `ndm:u` arbitrates access to the console's network interface between the
+//! background daemons (SpotPass, StreetPass, the infrastructure Wi-Fi
+//! connection, ...) and the foreground application. Most applications never
+//! need to touch it, but anything that wants exclusive use of the local
+//! communications hardware - such as ir:USER - needs to ask the daemons to
+//! step aside first.
+
+use crate::error::ResultCode;
+use crate::Result;
+
+/// A handle to the Network Daemon Manager (`ndm:u`) service.
+pub struct Ndm {
+    _service_handler: (),
+}
+
+impl Ndm {
+    /// Initialize the Ndm service.
+    pub fn new() -> Result<Self> {
+        unsafe {
+            ResultCode(ctru_sys::ndmuInit())?;
+        }
+
+        Ok(Self {
+            _service_handler: (),
+        })
+    }
+
+    /// Ask the background network daemons to suspend and move the console
+    /// into `state`, returning a guard that owns `self` and holds the
+    /// exclusive state for as long as it's alive.
+    ///
+    /// The daemons are resumed again when the returned [`ExclusiveStateGuard`]
+    /// is dropped, or earlier via [`ExclusiveStateGuard::leave`] (which hands
+    /// the `Ndm` back).
+    pub fn enter_exclusive_state(self, state: ExclusiveState) -> Result<ExclusiveStateGuard> {
+        unsafe {
+            ResultCode(ctru_sys::ndmuEnterExclusiveState(state.into()))?;
+        }
+
+        Ok(ExclusiveStateGuard {
+            ndm: Some(self),
+            left: false,
+        })
+    }
+}
+
+impl Drop for Ndm {
+    fn drop(&mut self) {
+        unsafe {
+            ctru_sys::ndmuExit();
+        }
+    }
+}
+
+/// The exclusive state that can be requested from the Ndm service.
+///
+/// See `ctru_sys::NDM_ExclusiveState` for the values these map to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExclusiveState {
+    /// Exclusive access to the infrastructure (internet-connected) Wi-Fi.
+    Infrastructure,
+    /// Exclusive access to local communications (e.g. ir:USER, local-play).
+    LocalCommunications,
+    /// Exclusive access for StreetPass exchanges.
+    StreetPass,
+    /// Exclusive access for StreetPass data transfers.
+    StreetPassData,
+}
+
+impl From<ExclusiveState> for ctru_sys::NDM_ExclusiveState {
+    fn from(state: ExclusiveState) -> Self {
+        match state {
+            ExclusiveState::Infrastructure => Self::EXCLUSIVE_STATE_INFRASTRUCTURE,
+            ExclusiveState::LocalCommunications => Self::EXCLUSIVE_STATE_LOCAL_COMMUNICATIONS,
+            ExclusiveState::StreetPass => Self::EXCLUSIVE_STATE_STREETPASS,
+            ExclusiveState::StreetPassData => Self::EXCLUSIVE_STATE_STREETPASS_DATA,
+        }
+    }
+}
+
+/// RAII guard owning an [`Ndm`] that has been put into an exclusive state.
+///
+/// While this guard is alive, the background network daemons are suspended.
+/// They're resumed again when the guard is dropped, or earlier by calling
+/// [`leave`](Self::leave), which hands the underlying `Ndm` back.
+#[must_use]
+pub struct ExclusiveStateGuard {
+    ndm: Option<Ndm>,
+    left: bool,
+}
+
+impl ExclusiveStateGuard {
+    /// Leave the exclusive state early, resuming the background daemons and
+    /// returning the underlying [`Ndm`].
+    ///
+    /// This happens automatically on drop; call this directly when the
+    /// caller wants the `Ndm` back, or needs to observe whether leaving
+    /// succeeded.
+    pub fn leave(mut self) -> Result<Ndm> {
+        self.leave_inner()?;
+        Ok(self.ndm.take().expect("ExclusiveStateGuard::ndm taken twice"))
+    }
+
+    fn leave_inner(&mut self) -> Result<()> {
+        if self.left {
+            return Ok(());
+        }
+
+        unsafe {
+            ResultCode(ctru_sys::ndmuLeaveExclusiveState())?;
+        }
+
+        self.left = true;
+        Ok(())
+    }
+}
+
+impl Drop for ExclusiveStateGuard {
+    fn drop(&mut self) {
+        // Dropping is infallible; the state is left best-effort and errors
+        // here have no caller left to observe them.
+        let _ = self.leave_inner();
+    }
+}